@@ -0,0 +1,41 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use ena::undo_log::{Rollback, Snapshots, VecLog};
+
+struct NeverRollback;
+
+impl Rollback<u32> for NeverRollback {
+    fn reverse(&mut self, _undo: u32) {
+        panic!("no changes were made, reverse() should not be called");
+    }
+}
+
+/// Opens and immediately commits a snapshot, without logging any actions in between. This is the
+/// common case for code that speculatively opens a snapshot but usually doesn't need to roll it
+/// back, and it should stay allocation-free.
+fn snapshot_commit_no_changes(c: &mut Criterion) {
+    c.bench_function("snapshot_commit_no_changes", |b| {
+        let mut log: VecLog<u32> = VecLog::default();
+        b.iter(|| {
+            let snapshot = log.start_snapshot();
+            log.commit(snapshot);
+        });
+    });
+}
+
+/// Same as above, but rolling back instead of committing.
+fn snapshot_rollback_no_changes(c: &mut Criterion) {
+    c.bench_function("snapshot_rollback_no_changes", |b| {
+        let mut log: VecLog<u32> = VecLog::default();
+        b.iter(|| {
+            let snapshot = log.start_snapshot();
+            log.rollback_to(|| NeverRollback, snapshot);
+        });
+    });
+}
+
+criterion_group!(
+    benches,
+    snapshot_commit_no_changes,
+    snapshot_rollback_no_changes
+);
+criterion_main!(benches);
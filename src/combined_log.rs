@@ -0,0 +1,192 @@
+//! Combinator for snapshotting several independent, heterogeneous `Snapshotted` values as a
+//! single atomic unit.
+//!
+//! Inference contexts typically keep more than one table (think separate type/int/float tables)
+//! that must all be rolled back together to a single point. `CombinedLog` wraps a tuple of
+//! `Snapshotted` values so that one `start_snapshot`/`rollback_to`/`commit` cycle drives all of
+//! them in lock-step, instead of callers threading a separate snapshot token per table.
+//!
+//! Since `Snapshotted` is also implemented for `&mut T`, a `CombinedLog` can be built ad hoc out
+//! of fields that are kept separately the rest of the time, e.g.
+//! `CombinedLog((&mut cx.type_vars, &mut cx.int_vars))`, rather than requiring the tables to be
+//! moved permanently into the tuple.
+
+use crate::undo_log::Snapshotted;
+
+/// Wraps a tuple of `Snapshotted` values so they can be snapshotted and rolled back together.
+pub struct CombinedLog<T>(pub T);
+
+/// The snapshot produced by a `CombinedLog`: one sub-snapshot per combined value, in the same
+/// order the values appear in the tuple.
+pub struct CombinedSnapshot<T>(T);
+
+/// Implements `Snapshotted` for `CombinedLog` over a tuple of a given arity.
+///
+/// Each invocation lists the tuple's type/binding names once in declaration order (`A: a / a_s`,
+/// ...) and again in the order children should be rolled back/committed in -- the reverse of the
+/// order their snapshots were taken in, matching the stack discipline a single `VecLog` enforces
+/// on its own snapshots. This way, supporting another arity is one macro invocation, not a
+/// hand-duplicated impl block.
+macro_rules! combined_log_tuple {
+    ($($ty:ident : $var:ident / $snap:ident),+ ; rev: $($rvar:ident / $rsnap:ident),+) => {
+        impl<$($ty),+> Snapshotted for CombinedLog<($($ty,)+)>
+        where
+            $($ty: Snapshotted,)+
+        {
+            type Snapshot = CombinedSnapshot<($($ty::Snapshot,)+)>;
+
+            fn num_open_snapshots(&self) -> usize {
+                let ($($var,)+) = &self.0;
+                let depths = [$($var.num_open_snapshots()),+];
+                assert!(
+                    depths.iter().all(|depth| *depth == depths[0]),
+                    "CombinedLog children are not all at the same snapshot depth"
+                );
+                depths[0]
+            }
+
+            fn start_snapshot(&mut self) -> Self::Snapshot {
+                self.num_open_snapshots();
+                let ($($var,)+) = &mut self.0;
+                CombinedSnapshot(($($var.start_snapshot(),)+))
+            }
+
+            fn rollback_to(&mut self, snapshot: Self::Snapshot) {
+                self.num_open_snapshots();
+                let ($($var,)+) = &mut self.0;
+                let ($($snap,)+) = snapshot.0;
+                $($rvar.rollback_to($rsnap);)+
+            }
+
+            fn commit(&mut self, snapshot: Self::Snapshot) {
+                self.num_open_snapshots();
+                let ($($var,)+) = &mut self.0;
+                let ($($snap,)+) = snapshot.0;
+                $($rvar.commit($rsnap);)+
+            }
+        }
+    };
+}
+
+combined_log_tuple!(A: a / a_s, B: b / b_s; rev: b / b_s, a / a_s);
+combined_log_tuple!(A: a / a_s, B: b / b_s, C: c / c_s; rev: c / c_s, b / b_s, a / a_s);
+combined_log_tuple!(
+    A: a / a_s, B: b / b_s, C: c / c_s, D: d / d_s;
+    rev: d / d_s, c / c_s, b / b_s, a / a_s
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::snapshot_map::SnapshotMap;
+    use crate::snapshot_vec::{SnapshotVec, SnapshotVecDelegate};
+
+    struct TestDelegate;
+
+    impl SnapshotVecDelegate for TestDelegate {
+        type Value = i32;
+        type Undo = ();
+
+        fn reverse(_values: &mut Vec<i32>, _action: ()) {}
+    }
+
+    type TestVec = SnapshotVec<TestDelegate>;
+    type TestMap = SnapshotMap<&'static str, i32>;
+
+    #[test]
+    fn owned_tuple_rollback_undoes_both_children() {
+        let mut vec: TestVec = SnapshotVec::new();
+        let mut map: TestMap = SnapshotMap::new();
+        vec.push(1);
+        map.insert("a", 1);
+
+        let mut combined = CombinedLog((vec, map));
+        let snapshot = combined.start_snapshot();
+        combined.0 .0.push(2);
+        combined.0 .1.insert("a", 2);
+        combined.rollback_to(snapshot);
+
+        assert_eq!(combined.0 .0.len(), 1);
+        assert_eq!(combined.0 .1.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn owned_tuple_commit_keeps_both_children() {
+        let mut combined = CombinedLog((TestVec::new(), TestMap::new()));
+
+        let snapshot = combined.start_snapshot();
+        combined.0 .0.push(1);
+        combined.0 .1.insert("a", 1);
+        combined.commit(snapshot);
+
+        assert_eq!(combined.0 .0.len(), 1);
+        assert_eq!(combined.0 .1.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn ad_hoc_mut_ref_tuple_rolls_back_the_originals() {
+        let mut vec: TestVec = SnapshotVec::new();
+        let mut map: TestMap = SnapshotMap::new();
+        vec.push(1);
+        map.insert("a", 1);
+
+        let mut combined = CombinedLog((&mut vec, &mut map));
+        let snapshot = combined.start_snapshot();
+        combined.0 .0.push(2);
+        combined.0 .1.insert("a", 2);
+        combined.rollback_to(snapshot);
+
+        assert_eq!(vec.len(), 1);
+        assert_eq!(map.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn guard_dropped_without_commit_rolls_back_both_children() {
+        let mut vec: TestVec = SnapshotVec::new();
+        let mut map: TestMap = SnapshotMap::new();
+        vec.push(1);
+        map.insert("a", 1);
+
+        {
+            let mut combined = CombinedLog((&mut vec, &mut map));
+            let mut guard = combined.snapshot_guard();
+            guard.0 .0.push(2);
+            guard.0 .1.insert("a", 2);
+            // The guard is dropped here without `commit`, so it should roll back.
+        }
+
+        assert_eq!(vec.len(), 1);
+        assert_eq!(map.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn guard_commit_keeps_changes_in_both_children() {
+        let mut vec: TestVec = SnapshotVec::new();
+        let mut map: TestMap = SnapshotMap::new();
+
+        let mut combined = CombinedLog((&mut vec, &mut map));
+        let mut guard = combined.snapshot_guard();
+        guard.0 .0.push(1);
+        guard.0 .1.insert("a", 1);
+        guard.commit();
+
+        assert_eq!(vec.len(), 1);
+        assert_eq!(map.get("a"), Some(&1));
+    }
+
+    #[test]
+    #[should_panic(expected = "CombinedLog children are not all at the same snapshot depth")]
+    fn resolving_after_an_out_of_band_child_snapshot_panics() {
+        let mut vec: TestVec = SnapshotVec::new();
+        let mut map: TestMap = SnapshotMap::new();
+
+        let mut combined = CombinedLog((&mut vec, &mut map));
+        let snapshot = combined.start_snapshot();
+
+        // Open an extra snapshot directly on one child, bypassing `CombinedLog`, so the children
+        // fall out of sync.
+        combined.0 .0.start_snapshot();
+
+        combined.rollback_to(snapshot);
+    }
+}
@@ -0,0 +1,15 @@
+//! Various data structures and algorithms for unifying and undoing state,
+//! as needed for type inference and other similar algorithms.
+//!
+//! The structures in this crate are all built around the same basic idea: a
+//! log of `UndoLog` actions is kept alongside some piece of data, and a
+//! "snapshot" is just a marker into that log. Rolling back to a snapshot
+//! means walking the log backwards and reversing each action in turn.
+
+#[macro_use]
+extern crate log;
+
+pub mod combined_log;
+pub mod snapshot_map;
+pub mod snapshot_vec;
+pub mod undo_log;
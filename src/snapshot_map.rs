@@ -0,0 +1,267 @@
+//! A map type whose insertions, overwrites and removals can be rolled back, for use alongside
+//! `SnapshotVec` and other snapshottable data structures that share the same undo log.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::ops;
+
+use crate::undo_log::{Rollback, Snapshots, Snapshotted, UndoLogs, VecLog};
+
+/// Actions that can be undone on a `SnapshotMap<K, V>`.
+#[derive(Clone, Debug)]
+pub enum UndoLog<K, V> {
+    /// The key was freshly inserted and did not previously have a value.
+    Inserted(K),
+    /// The key already had a value, which was overwritten with a new one.
+    Overwrite(K, V),
+    /// The key was removed, along with its value.
+    Purged(K, V),
+}
+
+/// A map of `K` to `V` whose mutations are tracked in an undo log, so that they can be rolled
+/// back as part of a snapshot.
+///
+/// `L` is the undo log backing the map; it defaults to a private `VecLog`, but can be any
+/// `UndoLogs<UndoLog<K, V>>`, including one borrowed from (and shared with) an outer data
+/// structure so that a `SnapshotMap` and a `SnapshotVec` can be rolled back to the same snapshot.
+pub struct SnapshotMap<K, V, L = VecLog<UndoLog<K, V>>>
+where
+    K: Eq + Hash,
+{
+    map: HashMap<K, V>,
+    undo_log: L,
+}
+
+impl<K, V, L> Default for SnapshotMap<K, V, L>
+where
+    K: Eq + Hash,
+    L: Default,
+{
+    fn default() -> Self {
+        SnapshotMap {
+            map: HashMap::default(),
+            undo_log: L::default(),
+        }
+    }
+}
+
+impl<K, V, L> SnapshotMap<K, V, L>
+where
+    K: Eq + Hash,
+    L: Default,
+{
+    pub fn new() -> Self {
+        SnapshotMap::default()
+    }
+}
+
+impl<K, V, L> SnapshotMap<K, V, L>
+where
+    K: Eq + Hash,
+{
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn get<Q: ?Sized + Eq + Hash>(&self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+    {
+        self.map.get(key)
+    }
+
+    pub fn contains_key<Q: ?Sized + Eq + Hash>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+    {
+        self.map.contains_key(key)
+    }
+}
+
+impl<K, V, L> SnapshotMap<K, V, L>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Inserts `value` for `key`, returning the previous value (if any). Logs `Inserted` if the
+    /// key was vacant, or `Overwrite` carrying the old value otherwise.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V>
+    where
+        L: UndoLogs<UndoLog<K, V>>,
+    {
+        // Only bother recording an undo action if it could ever be used: `num_open_snapshots`
+        // (not log emptiness) is what tells us whether a rollback could still reach this point.
+        let in_snapshot = self.undo_log.in_snapshot();
+        match self.map.insert(key.clone(), value) {
+            None => {
+                if in_snapshot {
+                    self.undo_log.push(UndoLog::Inserted(key));
+                }
+                None
+            }
+            Some(old_value) => {
+                if in_snapshot {
+                    self.undo_log
+                        .push(UndoLog::Overwrite(key, old_value.clone()));
+                }
+                Some(old_value)
+            }
+        }
+    }
+
+    /// Removes `key` from the map, returning its value (if any) and logging a `Purged` undo
+    /// action so the removal can be reversed.
+    pub fn remove(&mut self, key: K) -> Option<V>
+    where
+        L: UndoLogs<UndoLog<K, V>>,
+    {
+        let in_snapshot = self.undo_log.in_snapshot();
+        match self.map.remove(&key) {
+            Some(value) => {
+                if in_snapshot {
+                    self.undo_log.push(UndoLog::Purged(key, value.clone()));
+                }
+                Some(value)
+            }
+            None => None,
+        }
+    }
+
+    /// Empties the map. Each removed entry is logged as a `Purged` undo action.
+    pub fn clear(&mut self)
+    where
+        L: UndoLogs<UndoLog<K, V>>,
+    {
+        let in_snapshot = self.undo_log.in_snapshot();
+        let map = std::mem::take(&mut self.map);
+        for (key, value) in map {
+            if !in_snapshot {
+                continue;
+            }
+            self.undo_log.push(UndoLog::Purged(key, value));
+        }
+    }
+
+    pub fn start_snapshot(&mut self) -> L::Snapshot
+    where
+        L: Snapshots<UndoLog<K, V>>,
+    {
+        self.undo_log.start_snapshot()
+    }
+
+    pub fn rollback_to(&mut self, snapshot: L::Snapshot)
+    where
+        L: Snapshots<UndoLog<K, V>>,
+    {
+        let SnapshotMap { map, undo_log } = self;
+        undo_log.rollback_to(|| map, snapshot)
+    }
+
+    pub fn commit(&mut self, snapshot: L::Snapshot)
+    where
+        L: Snapshots<UndoLog<K, V>>,
+    {
+        self.undo_log.commit(snapshot)
+    }
+}
+
+impl<K, V, L> Snapshotted for SnapshotMap<K, V, L>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    L: Snapshots<UndoLog<K, V>>,
+{
+    type Snapshot = L::Snapshot;
+
+    fn num_open_snapshots(&self) -> usize {
+        self.undo_log.num_open_snapshots()
+    }
+
+    fn start_snapshot(&mut self) -> Self::Snapshot {
+        SnapshotMap::start_snapshot(self)
+    }
+
+    fn rollback_to(&mut self, snapshot: Self::Snapshot) {
+        SnapshotMap::rollback_to(self, snapshot)
+    }
+
+    fn commit(&mut self, snapshot: Self::Snapshot) {
+        SnapshotMap::commit(self, snapshot)
+    }
+}
+
+impl<K, V, L> ops::Index<&K> for SnapshotMap<K, V, L>
+where
+    K: Eq + Hash,
+{
+    type Output = V;
+    fn index(&self, key: &K) -> &V {
+        self.map.get(key).unwrap()
+    }
+}
+
+impl<K: Eq + Hash, V> Rollback<UndoLog<K, V>> for HashMap<K, V> {
+    fn reverse(&mut self, undo: UndoLog<K, V>) {
+        match undo {
+            UndoLog::Inserted(key) => {
+                self.remove(&key);
+            }
+            UndoLog::Overwrite(key, old_value) => {
+                self.insert(key, old_value);
+            }
+            UndoLog::Purged(key, value) => {
+                self.insert(key, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type TestMap = SnapshotMap<&'static str, i32>;
+
+    #[test]
+    fn insert_then_rollback_undoes_it() {
+        let mut map: TestMap = SnapshotMap::new();
+        map.insert("a", 1);
+
+        let snapshot = map.start_snapshot();
+        map.insert("a", 2);
+        map.insert("b", 3);
+        map.rollback_to(snapshot);
+
+        assert_eq!(map.get("a"), Some(&1));
+        assert_eq!(map.get("b"), None);
+    }
+
+    #[test]
+    fn insert_then_commit_keeps_it() {
+        let mut map: TestMap = SnapshotMap::new();
+
+        let snapshot = map.start_snapshot();
+        map.insert("a", 1);
+        map.commit(snapshot);
+
+        assert_eq!(map.get("a"), Some(&1));
+    }
+
+    #[test]
+    fn remove_then_rollback_restores_it() {
+        let mut map: TestMap = SnapshotMap::new();
+        map.insert("a", 1);
+
+        let snapshot = map.start_snapshot();
+        map.remove("a");
+        assert_eq!(map.get("a"), None);
+        map.rollback_to(snapshot);
+
+        assert_eq!(map.get("a"), Some(&1));
+    }
+}
@@ -0,0 +1,291 @@
+//! A vector type which allows its elements to be mutated and have the mutation rolled back,
+//! piggy-backing on the same undo log that other snapshottable data structures use.
+//!
+//! Unlike `undo_log::VecLog`, which is a raw log of undo actions with no opinion about what is
+//! being logged, `SnapshotVec` is a complete data structure: it owns the vector of values along
+//! with the undo log that records how to reverse mutations made to it, and implements `Rollback`
+//! so that it can be the target of a `Snapshots::rollback_to` call.
+
+use crate::undo_log::{Rollback, Snapshots, Snapshotted, UndoLogs, VecLog};
+
+/// A trait implemented by values stored in a `SnapshotVec`, describing how to reverse the one
+/// kind of mutation that `SnapshotVec` cannot record on its own: changes made through a
+/// `get_mut` handle.
+pub trait SnapshotVecDelegate {
+    type Value;
+    type Undo;
+
+    fn reverse(values: &mut Vec<Self::Value>, action: Self::Undo);
+}
+
+/// Actions that can be undone on a `SnapshotVec<D>`.
+#[derive(Debug)]
+pub enum UndoLog<D: SnapshotVecDelegate> {
+    /// A new element was pushed at the given index.
+    NewElem(usize),
+    /// The element at the given index had its old value overwritten.
+    SetElem(usize, D::Value),
+    /// A delegate-specific action, recorded by `record` for mutations that happened through
+    /// `get_mut` and so could not be captured automatically.
+    Other(D::Undo),
+}
+
+/// A vector of values of type `D::Value` whose mutations are tracked in an undo log, so that
+/// they can be rolled back as part of a snapshot.
+///
+/// `L` is the undo log backing the vector; it defaults to a private `VecLog`, but can be any
+/// `UndoLogs<UndoLog<D>>` (including one shared with other snapshottable data structures).
+/// `CombinedLog` does not share a log: it snapshots several values that each keep their own.
+pub struct SnapshotVec<D: SnapshotVecDelegate, L = VecLog<UndoLog<D>>> {
+    values: Vec<D::Value>,
+    undo_log: L,
+}
+
+impl<D: SnapshotVecDelegate, L: Default> Default for SnapshotVec<D, L> {
+    fn default() -> Self {
+        SnapshotVec {
+            values: Vec::new(),
+            undo_log: L::default(),
+        }
+    }
+}
+
+impl<D: SnapshotVecDelegate, L: Default> SnapshotVec<D, L> {
+    pub fn new() -> Self {
+        SnapshotVec::default()
+    }
+
+    pub fn with_capacity(capacity: usize) -> Self {
+        SnapshotVec {
+            values: Vec::with_capacity(capacity),
+            undo_log: L::default(),
+        }
+    }
+}
+
+impl<D: SnapshotVecDelegate, L> SnapshotVec<D, L> {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> &D::Value {
+        &self.values[index]
+    }
+
+    /// Returns a mutable handle to the element at `index`. Unlike `set`/`update`, this does not
+    /// log an undo action on its own, since there is no way to know in advance what will be done
+    /// with the returned reference. Callers that mutate the value through this handle are
+    /// responsible for calling `record` with an action that reverses the change.
+    pub fn get_mut(&mut self, index: usize) -> &mut D::Value {
+        &mut self.values[index]
+    }
+
+    /// Records a delegate-specific undo action, for use alongside mutations made through
+    /// `get_mut` that `SnapshotVec` could not log automatically.
+    pub fn record(&mut self, action: D::Undo)
+    where
+        L: UndoLogs<UndoLog<D>>,
+    {
+        self.undo_log.push(UndoLog::Other(action));
+    }
+
+    /// Pushes `elem` onto the vector, returning the index it was stored at, and logs a
+    /// `NewElem` undo action.
+    pub fn push(&mut self, elem: D::Value) -> usize
+    where
+        L: UndoLogs<UndoLog<D>>,
+    {
+        let len = self.values.len();
+        self.values.push(elem);
+        self.undo_log.push(UndoLog::NewElem(len));
+        len
+    }
+
+    /// Overwrites the element at `index` with `new_elem`, logging a `SetElem` undo action that
+    /// carries the previous value.
+    pub fn set(&mut self, index: usize, new_elem: D::Value)
+    where
+        L: UndoLogs<UndoLog<D>>,
+    {
+        let old_elem = std::mem::replace(&mut self.values[index], new_elem);
+        self.undo_log.push(UndoLog::SetElem(index, old_elem));
+    }
+
+    /// Updates the element at `index` in place using `op`, logging a `SetElem` undo action that
+    /// carries the previous value.
+    pub fn update<OP>(&mut self, index: usize, op: OP)
+    where
+        OP: FnOnce(&mut D::Value),
+        D::Value: Clone,
+        L: UndoLogs<UndoLog<D>>,
+    {
+        // Only bother cloning the old value if it could ever be used: outside of a snapshot,
+        // `push` below just drops the undo action on the floor anyway.
+        if self.undo_log.in_snapshot() {
+            let old_elem = self.values[index].clone();
+            op(&mut self.values[index]);
+            self.undo_log.push(UndoLog::SetElem(index, old_elem));
+        } else {
+            op(&mut self.values[index]);
+        }
+    }
+
+    pub fn start_snapshot(&mut self) -> L::Snapshot
+    where
+        L: Snapshots<UndoLog<D>>,
+    {
+        self.undo_log.start_snapshot()
+    }
+
+    pub fn rollback_to(&mut self, snapshot: L::Snapshot)
+    where
+        L: Snapshots<UndoLog<D>>,
+    {
+        let SnapshotVec { values, undo_log } = self;
+        undo_log.rollback_to(|| values, snapshot)
+    }
+
+    pub fn commit(&mut self, snapshot: L::Snapshot)
+    where
+        L: Snapshots<UndoLog<D>>,
+    {
+        self.undo_log.commit(snapshot)
+    }
+}
+
+impl<D: SnapshotVecDelegate, L: Snapshots<UndoLog<D>>> Snapshotted for SnapshotVec<D, L> {
+    type Snapshot = L::Snapshot;
+
+    fn num_open_snapshots(&self) -> usize {
+        self.undo_log.num_open_snapshots()
+    }
+
+    fn start_snapshot(&mut self) -> Self::Snapshot {
+        SnapshotVec::start_snapshot(self)
+    }
+
+    fn rollback_to(&mut self, snapshot: Self::Snapshot) {
+        SnapshotVec::rollback_to(self, snapshot)
+    }
+
+    fn commit(&mut self, snapshot: Self::Snapshot) {
+        SnapshotVec::commit(self, snapshot)
+    }
+}
+
+impl<D: SnapshotVecDelegate, L> std::ops::Index<usize> for SnapshotVec<D, L> {
+    type Output = D::Value;
+    fn index(&self, index: usize) -> &D::Value {
+        self.get(index)
+    }
+}
+
+impl<D: SnapshotVecDelegate> Rollback<UndoLog<D>> for Vec<D::Value> {
+    fn reverse(&mut self, undo: UndoLog<D>) {
+        match undo {
+            UndoLog::NewElem(i) => {
+                self.pop();
+                debug_assert_eq!(self.len(), i);
+            }
+            UndoLog::SetElem(i, old_elem) => {
+                self[i] = old_elem;
+            }
+            UndoLog::Other(undo) => D::reverse(self, undo),
+        }
+    }
+}
+
+impl<D: SnapshotVecDelegate, L> Rollback<UndoLog<D>> for SnapshotVec<D, L> {
+    fn reverse(&mut self, undo: UndoLog<D>) {
+        Rollback::reverse(&mut self.values, undo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestDelegate;
+
+    impl SnapshotVecDelegate for TestDelegate {
+        type Value = i32;
+        type Undo = ();
+
+        fn reverse(_values: &mut Vec<i32>, _action: ()) {}
+    }
+
+    type TestVec = SnapshotVec<TestDelegate>;
+
+    #[test]
+    fn push_then_rollback_undoes_it() {
+        let mut vec: TestVec = SnapshotVec::new();
+        vec.push(1);
+
+        let snapshot = vec.start_snapshot();
+        vec.push(2);
+        vec.set(0, 10);
+        vec.rollback_to(snapshot);
+
+        assert_eq!(vec.len(), 1);
+        assert_eq!(*vec.get(0), 1);
+    }
+
+    #[test]
+    fn push_then_commit_keeps_it() {
+        let mut vec: TestVec = SnapshotVec::new();
+        vec.push(1);
+
+        let snapshot = vec.start_snapshot();
+        vec.push(2);
+        vec.commit(snapshot);
+
+        assert_eq!(vec.len(), 2);
+        assert_eq!(*vec.get(1), 2);
+    }
+
+    #[test]
+    fn nested_snapshots_roll_back_independently() {
+        let mut vec: TestVec = SnapshotVec::new();
+        vec.push(1);
+
+        let outer = vec.start_snapshot();
+        vec.push(2);
+        let inner = vec.start_snapshot();
+        vec.push(3);
+        vec.rollback_to(inner);
+        assert_eq!(vec.len(), 2);
+
+        vec.rollback_to(outer);
+        assert_eq!(vec.len(), 1);
+    }
+
+    #[test]
+    fn snapshot_guard_pushes_before_dropping_rolls_back() {
+        let mut vec: TestVec = SnapshotVec::new();
+        vec.push(1);
+
+        {
+            let mut guard = vec.snapshot_guard();
+            guard.push(2);
+            // The guard is dropped here without `commit`, so it should roll back.
+        }
+
+        assert_eq!(vec.len(), 1);
+    }
+
+    #[test]
+    fn snapshot_guard_commit_keeps_changes() {
+        let mut vec: TestVec = SnapshotVec::new();
+        vec.push(1);
+
+        let mut guard = vec.snapshot_guard();
+        guard.push(2);
+        guard.commit();
+
+        assert_eq!(vec.len(), 2);
+    }
+}
@@ -60,6 +60,28 @@ pub trait Snapshots<T>: UndoLogs<T> {
         R: Rollback<T>;
 
     fn commit(&mut self, snapshot: Self::Snapshot);
+
+    /// Starts a snapshot and wraps it in a `SnapshotGuard` that rolls back automatically on
+    /// `Drop`. This avoids the failure mode where a panic or early return between `start_snapshot`
+    /// and its resolution leaks an open snapshot: the guard's default outcome is rollback, and
+    /// callers opt into the other outcome explicitly by calling `SnapshotGuard::commit`.
+    fn snapshot_guard<'a, R>(
+        &'a mut self,
+        values: impl FnOnce() -> R + 'a,
+    ) -> SnapshotGuard<'a, Self, T>
+    where
+        Self: Sized,
+        R: Rollback<T> + 'a,
+    {
+        let snapshot = self.start_snapshot();
+        let values: BoxedValues<'a, T> =
+            Box::new(move || Box::new(values()) as BoxedRollback<'a, T>);
+        SnapshotGuard {
+            log: self,
+            snapshot: Some(snapshot),
+            values: Some(values),
+        }
+    }
 }
 
 impl<T, U> Snapshots<T> for &'_ mut U
@@ -89,6 +111,97 @@ where
     }
 }
 
+/// Implemented by types which own both their data and their undo log, and so can expose the bare
+/// `start_snapshot`/`rollback_to`/`commit` cycle directly, with no `values` closure required
+/// (unlike `Snapshots<T>`, whose `rollback_to` needs to be told what to roll back).
+///
+/// `SnapshotVec` and `SnapshotMap` implement this. `CombinedLog` builds a single `Snapshotted`
+/// out of several others, so that multiple such tables can be snapshotted and rolled back as one
+/// atomic unit; see the `combined_log` module.
+pub trait Snapshotted {
+    type Snapshot;
+
+    fn num_open_snapshots(&self) -> usize;
+    fn start_snapshot(&mut self) -> Self::Snapshot;
+    fn rollback_to(&mut self, snapshot: Self::Snapshot);
+    fn commit(&mut self, snapshot: Self::Snapshot);
+
+    /// Starts a snapshot and wraps it in a `SnapshottedGuard` that rolls back automatically on
+    /// `Drop`. Since `Self` already owns both its data and its undo log, the guard derefs
+    /// straight to `Self`, so callers can keep mutating it for the guarded window and only need
+    /// to call `SnapshottedGuard::commit` explicitly to keep the changes instead of discarding
+    /// them.
+    fn snapshot_guard(&mut self) -> SnapshottedGuard<'_, Self>
+    where
+        Self: Sized,
+    {
+        let snapshot = self.start_snapshot();
+        SnapshottedGuard {
+            inner: self,
+            snapshot: Some(snapshot),
+        }
+    }
+}
+
+impl<T> Snapshotted for &'_ mut T
+where
+    T: Snapshotted,
+{
+    type Snapshot = T::Snapshot;
+
+    fn num_open_snapshots(&self) -> usize {
+        (**self).num_open_snapshots()
+    }
+    fn start_snapshot(&mut self) -> Self::Snapshot {
+        (**self).start_snapshot()
+    }
+    fn rollback_to(&mut self, snapshot: Self::Snapshot) {
+        (**self).rollback_to(snapshot)
+    }
+    fn commit(&mut self, snapshot: Self::Snapshot) {
+        (**self).commit(snapshot)
+    }
+}
+
+/// An RAII guard for an open snapshot, returned by `Snapshotted::snapshot_guard`.
+///
+/// Rolls back to the snapshot automatically on `Drop`; call `commit` to keep the changes made
+/// since the snapshot was taken instead.
+pub struct SnapshottedGuard<'a, S: Snapshotted> {
+    inner: &'a mut S,
+    // `None` once the guard has been consumed by `commit`.
+    snapshot: Option<S::Snapshot>,
+}
+
+impl<'a, S: Snapshotted> SnapshottedGuard<'a, S> {
+    /// Consumes the guard, committing the snapshot instead of rolling it back.
+    pub fn commit(mut self) {
+        let snapshot = self.snapshot.take().expect("snapshot already resolved");
+        self.inner.commit(snapshot);
+    }
+}
+
+impl<'a, S: Snapshotted> Drop for SnapshottedGuard<'a, S> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            self.inner.rollback_to(snapshot);
+        }
+    }
+}
+
+impl<'a, S: Snapshotted> std::ops::Deref for SnapshottedGuard<'a, S> {
+    type Target = S;
+    fn deref(&self) -> &S {
+        self.inner
+    }
+}
+
+impl<'a, S: Snapshotted> std::ops::DerefMut for SnapshottedGuard<'a, S> {
+    fn deref_mut(&mut self) -> &mut S {
+        self.inner
+    }
+}
+
 pub struct NoUndo;
 impl<T> UndoLogs<T> for NoUndo {
     fn num_open_snapshots(&self) -> usize {
@@ -119,7 +232,12 @@ impl<T> UndoLogs<T> for VecLog<T> {
         self.num_open_snapshots
     }
     fn push(&mut self, undo: T) {
-        self.log.push(undo);
+        // Outside of a snapshot there is nothing to ever roll back to, so there's no point
+        // logging the action; this keeps the backing `Vec` unallocated until the first action is
+        // pushed inside a real snapshot.
+        if self.in_snapshot() {
+            self.log.push(undo);
+        }
     }
     fn clear(&mut self) {
         self.log.clear();
@@ -152,7 +270,10 @@ impl<T> Snapshots<T> for VecLog<T> {
 
         self.assert_open_snapshot(&snapshot);
 
-        if self.log.len() > snapshot.undo_len {
+        // Fast path: a `start_snapshot` immediately followed by a `rollback_to` with nothing
+        // logged in between is common (e.g. speculative work that usually succeeds), and this
+        // avoids invoking `values()` or entering the pop loop at all in that case.
+        if self.has_changes(&snapshot) {
             let mut values = values();
             while self.log.len() > snapshot.undo_len {
                 values.reverse(self.log.pop().unwrap());
@@ -208,8 +329,143 @@ where
     }
 }
 
+impl<U> Rollback<U> for Box<dyn Rollback<U> + '_> {
+    fn reverse(&mut self, undo: U) {
+        (**self).reverse(undo)
+    }
+}
+
+type BoxedRollback<'a, T> = Box<dyn Rollback<T> + 'a>;
+type BoxedValues<'a, T> = Box<dyn FnOnce() -> BoxedRollback<'a, T> + 'a>;
+
+/// An RAII guard for an open snapshot, returned by `Snapshots::snapshot_guard`.
+///
+/// Rolls back to the snapshot automatically on `Drop`; call `commit` to keep the changes made
+/// since the snapshot was taken instead.
+pub struct SnapshotGuard<'a, S, T>
+where
+    S: Snapshots<T>,
+{
+    log: &'a mut S,
+    // `None` once the guard has been consumed by `commit`.
+    snapshot: Option<S::Snapshot>,
+    values: Option<BoxedValues<'a, T>>,
+}
+
+impl<'a, S, T> SnapshotGuard<'a, S, T>
+where
+    S: Snapshots<T>,
+{
+    /// Consumes the guard, committing the snapshot instead of rolling it back.
+    pub fn commit(mut self) {
+        let snapshot = self.snapshot.take().expect("snapshot already resolved");
+        self.log.commit(snapshot);
+    }
+}
+
+impl<'a, S, T> Drop for SnapshotGuard<'a, S, T>
+where
+    S: Snapshots<T>,
+{
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            let values = self.values.take().expect("snapshot already resolved");
+            self.log.rollback_to(values, snapshot);
+        }
+    }
+}
+
+// The guard borrows the log for its whole lifetime so that `rollback_to`/`commit` can be called
+// on it once resolved; `Deref`/`DerefMut` hand that borrow back to the caller in the meantime, so
+// actions can still be pushed (and any other `&mut S` method called) during the guarded window.
+impl<'a, S, T> std::ops::Deref for SnapshotGuard<'a, S, T>
+where
+    S: Snapshots<T>,
+{
+    type Target = S;
+    fn deref(&self) -> &S {
+        self.log
+    }
+}
+
+impl<'a, S, T> std::ops::DerefMut for SnapshotGuard<'a, S, T>
+where
+    S: Snapshots<T>,
+{
+    fn deref_mut(&mut self) -> &mut S {
+        self.log
+    }
+}
+
 /// Snapshots are tokens that should be created/consumed linearly.
 pub struct Snapshot {
     // Length of the undo log at the time the snapshot was taken.
     undo_len: usize,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct CountingRollback<'a>(&'a mut i32);
+
+    impl Rollback<i32> for CountingRollback<'_> {
+        fn reverse(&mut self, undo: i32) {
+            *self.0 += undo;
+        }
+    }
+
+    #[test]
+    fn push_then_rollback_undoes_it() {
+        let mut log: VecLog<i32> = VecLog::default();
+        let mut total = 0;
+
+        let snapshot = log.start_snapshot();
+        log.push(1);
+        log.push(2);
+        log.rollback_to(|| CountingRollback(&mut total), snapshot);
+
+        // Actions are replayed in reverse: 2, then 1.
+        assert_eq!(total, 3);
+        assert!(!log.in_snapshot());
+    }
+
+    #[test]
+    fn push_then_commit_closes_the_snapshot() {
+        let mut log: VecLog<i32> = VecLog::default();
+
+        let snapshot = log.start_snapshot();
+        log.push(1);
+        log.commit(snapshot);
+
+        assert!(!log.in_snapshot());
+    }
+
+    #[test]
+    fn snapshot_guard_pushes_before_dropping_rolls_back() {
+        let mut log: VecLog<i32> = VecLog::default();
+        let mut total = 0;
+
+        {
+            let mut guard = log.snapshot_guard(|| CountingRollback(&mut total));
+            guard.push(5);
+            // The guard is dropped here without `commit`, so it should roll back.
+        }
+
+        assert_eq!(total, 5);
+        assert!(!log.in_snapshot());
+    }
+
+    #[test]
+    fn snapshot_guard_commit_keeps_changes() {
+        let mut log: VecLog<i32> = VecLog::default();
+        let mut total = 0;
+
+        let mut guard = log.snapshot_guard(|| CountingRollback(&mut total));
+        guard.push(5);
+        guard.commit();
+
+        assert_eq!(total, 0);
+        assert!(!log.in_snapshot());
+    }
+}